@@ -0,0 +1,47 @@
+use nalgebra_glm as glm;
+
+/// Perspective camera used to build the projection and view matrices
+/// uploaded to the vertex shader each frame.
+pub struct Camera {
+    fov_y: f32,
+    aspect: f32,
+    near: f32,
+    far: f32,
+    position: glm::Vec3,
+    target: glm::Vec3,
+    up: glm::Vec3,
+}
+
+impl Camera {
+    pub fn new(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        Self {
+            fov_y,
+            aspect,
+            near,
+            far,
+            position: glm::vec3(0.0, 0.0, 3.0),
+            target: glm::vec3(0.0, 0.0, 0.0),
+            up: glm::vec3(0.0, 1.0, 0.0),
+        }
+    }
+
+    pub fn set_aspect(&mut self, aspect: f32) {
+        self.aspect = aspect;
+    }
+
+    /// Recomputes the aspect ratio from the canvas' current pixel size, to
+    /// be called after a resize so `projection_matrix` stays correct.
+    pub fn update_aspect_from_canvas(&mut self, canvas: &web_sys::HtmlCanvasElement) {
+        self.set_aspect(canvas.width() as f32 / canvas.height() as f32);
+    }
+
+    pub fn projection_matrix(&self) -> [f32; 16] {
+        let projection = glm::perspective(self.aspect, self.fov_y, self.near, self.far);
+        projection.as_slice().try_into().unwrap()
+    }
+
+    pub fn view_matrix(&self) -> [f32; 16] {
+        let view = glm::look_at(&self.position, &self.target, &self.up);
+        view.as_slice().try_into().unwrap()
+    }
+}
@@ -1,18 +1,31 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 use web_sys::{
+    WebGl2RenderingContext,
     WebGlProgram,
-    WebGlRenderingContext,
     WebGlBuffer,
     WebGlShader,
+    WebGlTexture,
     WebGlUniformLocation,
+    WebGlVertexArrayObject,
 };
 
 mod camera;
 use camera::Camera;
 
+mod uniform;
+use uniform::Uniform;
+
+mod renderer;
+use renderer::{RenderItem, Renderer};
+
+mod texture;
+use texture::load_texture;
+
 struct ProgramInfo {
     program: WebGlProgram,
     attrib_locations: HashMap<String, u32>,
@@ -21,7 +34,7 @@ struct ProgramInfo {
 
 impl ProgramInfo {
     pub fn new(
-        context: &WebGlRenderingContext,
+        context: &WebGl2RenderingContext,
         vert_shader: &WebGlShader,
         frag_shader: &WebGlShader,
     ) -> Result<Self, JsValue> {
@@ -39,19 +52,34 @@ impl ProgramInfo {
 
         let mut uniform_locations = HashMap::new();
 
-        uniform_locations.insert(
-            "colour".to_owned(),
-            context.get_uniform_location(
-                &program,
-                "u_colour"
-            ).unwrap()
-        );
-        uniform_locations.insert(
-            "model_view_matrix".to_owned(),
-            context.get_uniform_location(
+        if let Some(location) = context.get_uniform_location(&program, "u_colour") {
+            uniform_locations.insert("colour".to_owned(), location);
+        }
+        if let Some(location) = context.get_uniform_location(&program, "u_model_matrix") {
+            uniform_locations.insert("model_matrix".to_owned(), location);
+        }
+        if let Some(location) = context.get_uniform_location(&program, "u_projection_matrix") {
+            uniform_locations.insert("projection_matrix".to_owned(), location);
+        }
+        if let Some(location) = context.get_uniform_location(&program, "u_view_matrix") {
+            uniform_locations.insert("view_matrix".to_owned(), location);
+        }
+        if let Some(location) = context.get_uniform_location(&program, "u_sampler") {
+            uniform_locations.insert("sampler".to_owned(), location);
+        }
+        if let Some(location) = context.get_uniform_location(&program, "u_time") {
+            uniform_locations.insert("time".to_owned(), location);
+        }
+        if let Some(location) = context.get_uniform_location(&program, "u_frame") {
+            uniform_locations.insert("frame".to_owned(), location);
+        }
+
+        attrib_locations.insert(
+            "texture_coord".to_owned(),
+            context.get_attrib_location(
                 &program,
-                "u_model_view_matrix"
-            ).unwrap()
+                "a_texture_coord"
+            ) as u32
         );
 
         Ok(Self {
@@ -63,119 +91,85 @@ impl ProgramInfo {
 }
 
 fn draw_scene(
-    context: &WebGlRenderingContext,
+    context: &WebGl2RenderingContext,
+    vertex_count: i32,
 ) {
-    let vertex_count = 4;
     context.draw_arrays(
-        WebGlRenderingContext::TRIANGLE_STRIP,
+        WebGl2RenderingContext::TRIANGLE_STRIP,
         0,
-        vertex_count as i32,
-    );
-}
-
-fn set_uniform(
-    context: &WebGlRenderingContext,
-    program_info: &ProgramInfo,
-    uniform_name: &str,
-    data: &[f32; 4]
-) {
-    let colour_location = program_info.uniform_locations.get(uniform_name);
-
-    context.uniform4fv_with_f32_array(
-        colour_location,
-        data
+        vertex_count,
     );
 }
 
 fn set_uniforms(
-    context: &WebGlRenderingContext,
+    context: &WebGl2RenderingContext,
     program_info: &ProgramInfo,
+    uniforms: &HashMap<String, Uniform>,
 ) {
-    set_uniform(
-        context,
-        program_info,
-        "colour",
-        &[0., 1.0, 0.6, 1.0,],
-    );
-
-    let model_view_matrix_position = program_info.uniform_locations.get("model_view_matrix");
-
-    context.uniform_matrix4fv_with_f32_array(
-        model_view_matrix_position,
-        false,
-        &[
-            0.5, 0., 0., 0.,
-            0., 1., 0., 0.,
-            0., 0., 1., 0.,
-            0., 0., 0., 1.,
-        ],
-    );
+    for (name, value) in uniforms {
+        program_info.set_uniform(context, name, value);
+    }
 }
 
 fn prepare_scene(
-    context: &WebGlRenderingContext,
+    context: &WebGl2RenderingContext,
     program_info: &ProgramInfo,
-    buffer: &WebGlBuffer,
-    _camera: &Camera,
+    vao: &WebGlVertexArrayObject,
+    uniforms: &HashMap<String, Uniform>,
+    texture: Option<&WebGlTexture>,
+    camera: &Camera,
 ) {
-    context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(buffer));
-    let vertex_position = *program_info.attrib_locations.get("vertex_position").unwrap();
-
     context.use_program(Some(&program_info.program));
 
-    context.vertex_attrib_pointer_with_i32(
-        vertex_position,
-        3,
-        WebGlRenderingContext::FLOAT,
-        false,
-        0,
-        0
-    );
-    context.enable_vertex_attrib_array(
-        vertex_position
-    );
+    // The vertex attribute bindings were already captured into the VAO by
+    // `init_buffers`, so a frame only needs to rebind it rather than
+    // re-specifying `vertex_attrib_pointer`/`enable_vertex_attrib_array`.
+    context.bind_vertex_array(Some(vao));
 
     set_uniforms(
         context,
-        program_info
+        program_info,
+        uniforms,
+    );
+
+    program_info.set_uniform(
+        context,
+        "projection_matrix",
+        &Uniform::Mat4(camera.projection_matrix()),
     );
+    program_info.set_uniform(
+        context,
+        "view_matrix",
+        &Uniform::Mat4(camera.view_matrix()),
+    );
+
+    if let Some(texture) = texture {
+        if let Some(sampler_location) = program_info.uniform_locations.get("sampler") {
+            context.active_texture(WebGl2RenderingContext::TEXTURE0);
+            context.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(texture));
+            context.uniform1i(Some(sampler_location), 0);
+        }
+    }
 }
 
 fn clear_scene(
-    context: &WebGlRenderingContext,
+    context: &WebGl2RenderingContext,
 ) {
     context.clear_color(0.0, 0.0, 0.0, 1.0);
-    context.clear(WebGlRenderingContext::COLOR_BUFFER_BIT);
+    context.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
 }
 
-fn render_scene(
-    context: &WebGlRenderingContext,
+fn init_buffers(
+    context: &WebGl2RenderingContext,
     program_info: &ProgramInfo,
-    buffer: &WebGlBuffer,
-    camera: &Camera,
-) {
-    clear_scene(context);
-    prepare_scene(
-        context,
-        program_info,
-        buffer,
-        camera,
-    );
-    draw_scene(context);
-}
+    vertices: &[f32],
+    texture_coords: Option<&[f32]>,
+) -> Result<(WebGlBuffer, Option<WebGlBuffer>, WebGlVertexArrayObject), String> {
+    let vao = context.create_vertex_array().ok_or("failed to create vertex array object")?;
+    context.bind_vertex_array(Some(&vao));
 
-fn init_buffers(
-    context: &WebGlRenderingContext
-) -> Result<WebGlBuffer, String> {
     let buffer = context.create_buffer().ok_or("failed to create buffer")?;
-    context.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&buffer));
-
-    let vertices: [f32; 12] = [
-        -0.5,  0.5, 0.0,
-        0.5,  0.5, 0.0,
-        -0.5, -0.5, 0.0,
-        0.5, -0.5, 0.0,
-    ];
+    context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&buffer));
 
     // Note that `Float32Array::view` is somewhat dangerous (hence the
     // `unsafe`!). This is creating a raw view into our module's
@@ -186,20 +180,70 @@ fn init_buffers(
     // As a result, after `Float32Array::view` we have to be very careful not to
     // do any memory allocations before it's dropped.
     unsafe {
-        let vert_array = js_sys::Float32Array::view(&vertices);
+        let vert_array = js_sys::Float32Array::view(vertices);
 
         context.buffer_data_with_array_buffer_view(
-            WebGlRenderingContext::ARRAY_BUFFER,
+            WebGl2RenderingContext::ARRAY_BUFFER,
             &vert_array,
-            WebGlRenderingContext::STATIC_DRAW,
+            WebGl2RenderingContext::STATIC_DRAW,
         );
 
     }
-    Ok(buffer)
+
+    let vertex_position = *program_info.attrib_locations.get("vertex_position").unwrap();
+    context.vertex_attrib_pointer_with_i32(
+        vertex_position,
+        3,
+        WebGl2RenderingContext::FLOAT,
+        false,
+        0,
+        0
+    );
+    context.enable_vertex_attrib_array(
+        vertex_position
+    );
+
+    let texture_coord_buffer = match texture_coords {
+        Some(texture_coords) => {
+            let texture_coord_buffer = context.create_buffer().ok_or("failed to create buffer")?;
+            context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&texture_coord_buffer));
+
+            unsafe {
+                let texture_coord_array = js_sys::Float32Array::view(texture_coords);
+
+                context.buffer_data_with_array_buffer_view(
+                    WebGl2RenderingContext::ARRAY_BUFFER,
+                    &texture_coord_array,
+                    WebGl2RenderingContext::STATIC_DRAW,
+                );
+            }
+
+            let texture_coord = *program_info.attrib_locations.get("texture_coord").unwrap();
+            context.vertex_attrib_pointer_with_i32(
+                texture_coord,
+                2,
+                WebGl2RenderingContext::FLOAT,
+                false,
+                0,
+                0
+            );
+            context.enable_vertex_attrib_array(
+                texture_coord
+            );
+
+            Some(texture_coord_buffer)
+        }
+        None => None,
+    };
+
+    context.bind_vertex_array(None);
+    context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, None);
+
+    Ok((buffer, texture_coord_buffer, vao))
 }
 
 pub fn init_shader_program(
-    context: &WebGlRenderingContext,
+    context: &WebGl2RenderingContext,
     vert_shader: &WebGlShader,
     frag_shader: &WebGlShader,
 ) -> Result<WebGlProgram, String> {
@@ -212,7 +256,7 @@ pub fn init_shader_program(
     context.link_program(&program);
 
     if context
-        .get_program_parameter(&program, WebGlRenderingContext::LINK_STATUS)
+        .get_program_parameter(&program, WebGl2RenderingContext::LINK_STATUS)
         .as_bool()
         .unwrap_or(false)
     {
@@ -225,7 +269,7 @@ pub fn init_shader_program(
 }
 
 pub fn load_shader(
-    context: &WebGlRenderingContext,
+    context: &WebGl2RenderingContext,
     shader_type: u32,
     source: &str,
 ) -> Result<WebGlShader, String> {
@@ -236,7 +280,7 @@ pub fn load_shader(
     context.compile_shader(&shader);
 
     if context
-        .get_shader_parameter(&shader, WebGlRenderingContext::COMPILE_STATUS)
+        .get_shader_parameter(&shader, WebGl2RenderingContext::COMPILE_STATUS)
         .as_bool()
         .unwrap_or(false)
     {
@@ -250,59 +294,153 @@ pub fn load_shader(
     }
 }
 
-fn init_context() -> Result<WebGlRenderingContext, JsValue> {
+fn init_context() -> Result<(WebGl2RenderingContext, web_sys::HtmlCanvasElement), JsValue> {
     let document = web_sys::window().unwrap().document().unwrap();
     let canvas = document.get_element_by_id("canvas").unwrap();
     let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
 
     let context = canvas
-        .get_context("webgl")?
+        .get_context("webgl2")?
         .unwrap()
-        .dyn_into::<WebGlRenderingContext>()?;
+        .dyn_into::<WebGl2RenderingContext>()?;
 
-    Ok(context)
+    Ok((context, canvas))
 }
 
 #[wasm_bindgen(start)]
 pub fn start() -> Result<(), JsValue> {
-    let context = init_context()?;
-    let vert_shader = load_shader(
-        &context,
-        WebGlRenderingContext::VERTEX_SHADER,
-        r#"
-        attribute vec4 a_vertex_position;
+    let (context, canvas) = init_context()?;
+    let aspect = canvas.width() as f32 / canvas.height() as f32;
+    let camera = Camera::new(std::f32::consts::FRAC_PI_4, aspect, 0.1, 100.0);
+    let mut renderer = Renderer::new(context, camera);
 
-        uniform mat4 u_model_view_matrix;
-        // uniform mat4 uProjectionMatrix;
+    renderer.register_shader(
+        "basic",
+        r#"#version 300 es
+        in vec4 a_vertex_position;
+
+        uniform mat4 u_model_matrix;
+        uniform mat4 u_view_matrix;
+        uniform mat4 u_projection_matrix;
 
         void main() {
-            gl_Position = u_model_view_matrix * a_vertex_position;
-            // gl_Position = uProjectionMatrix * u_model_view_matrix * a_vertex_position;
+            gl_Position = u_projection_matrix * u_view_matrix * u_model_matrix * a_vertex_position;
+        }
+    "#,
+        r#"#version 300 es
+        precision mediump float;
+
+        uniform vec4 u_colour;
+
+        out vec4 out_colour;
+
+        void main() {
+            out_colour = u_colour;
         }
     "#,
     )?;
-    let frag_shader = load_shader(
-        &context,
-        WebGlRenderingContext::FRAGMENT_SHADER,
-        r#"
 
+    let mut uniforms = HashMap::new();
+    uniforms.insert("colour".to_owned(), Uniform::Vec4([0., 1.0, 0.6, 1.0]));
+    uniforms.insert(
+        "model_matrix".to_owned(),
+        Uniform::Mat4([
+            0.5, 0., 0., 0.,
+            0., 1., 0., 0.,
+            0., 0., 1., 0.,
+            0., 0., 0., 1.,
+        ]),
+    );
+
+    renderer.add_item(RenderItem {
+        vertices: vec![
+            -0.5,  0.5, 0.0,
+            0.5,  0.5, 0.0,
+            -0.5, -0.5, 0.0,
+            0.5, -0.5, 0.0,
+        ],
+        shader_name: "basic".to_owned(),
+        uniforms,
+        texture_coords: None,
+        texture: None,
+    })?;
+
+    renderer.register_shader(
+        "textured",
+        r#"#version 300 es
+        in vec4 a_vertex_position;
+        in vec2 a_texture_coord;
+
+        uniform mat4 u_model_matrix;
+        uniform mat4 u_view_matrix;
+        uniform mat4 u_projection_matrix;
+
+        out vec2 v_texture_coord;
+
+        void main() {
+            gl_Position = u_projection_matrix * u_view_matrix * u_model_matrix * a_vertex_position;
+            v_texture_coord = a_texture_coord;
+        }
+    "#,
+        r#"#version 300 es
         precision mediump float;
-        uniform vec4 u_colour;
+
+        uniform sampler2D u_sampler;
+
+        in vec2 v_texture_coord;
+        out vec4 out_colour;
 
         void main() {
-            gl_FragColor = u_colour;
+            out_colour = texture(u_sampler, v_texture_coord);
         }
     "#,
     )?;
-    let program_info = ProgramInfo::new(&context, &vert_shader, &frag_shader)?;
-    let positions_buffer = init_buffers(&context)?;
-    let camera = Camera::new();
-
-    render_scene(
-        &context,
-        &program_info,
-        &positions_buffer,
-        &camera
+
+    let texture = load_texture(renderer.context(), "texture.png")?;
+
+    let mut textured_uniforms = HashMap::new();
+    textured_uniforms.insert(
+        "model_matrix".to_owned(),
+        Uniform::Mat4([
+            0.5, 0., 0., 0.,
+            0., 0.5, 0., 0.,
+            0., 0., 0.5, 0.,
+            0.5, 0., 0., 1.,
+        ]),
     );
+
+    renderer.add_item(RenderItem {
+        vertices: vec![
+            -0.5,  0.5, 0.0,
+            0.5,  0.5, 0.0,
+            -0.5, -0.5, 0.0,
+            0.5, -0.5, 0.0,
+        ],
+        shader_name: "textured".to_owned(),
+        uniforms: textured_uniforms,
+        texture_coords: Some(vec![
+            0.0, 0.0,
+            1.0, 0.0,
+            0.0, 1.0,
+            1.0, 1.0,
+        ]),
+        texture: Some(texture),
+    })?;
+
+    let renderer = Rc::new(RefCell::new(renderer));
+
+    {
+        let renderer = renderer.clone();
+        let canvas = canvas.clone();
+        let on_resize = Closure::<dyn FnMut()>::new(move || {
+            renderer.borrow_mut().handle_resize(&canvas);
+        });
+        web_sys::window()
+            .unwrap()
+            .add_event_listener_with_callback("resize", on_resize.as_ref().unchecked_ref())?;
+        on_resize.forget();
+    }
+
+    Renderer::start_render_loop(renderer);
     Ok(())
-}
\ No newline at end of file
+}
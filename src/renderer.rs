@@ -0,0 +1,246 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{
+    OffscreenCanvas,
+    WebGl2RenderingContext,
+    WebGlBuffer,
+    WebGlTexture,
+    WebGlVertexArrayObject,
+};
+
+use crate::camera::Camera;
+use crate::uniform::Uniform;
+use crate::{clear_scene, draw_scene, init_buffers, load_shader, prepare_scene, ProgramInfo};
+
+/// A single piece of geometry to draw, described declaratively rather than
+/// by editing the renderer's core code.
+pub struct RenderItem {
+    pub vertices: Vec<f32>,
+    pub shader_name: String,
+    pub uniforms: HashMap<String, Uniform>,
+    pub texture_coords: Option<Vec<f32>>,
+    pub texture: Option<Rc<WebGlTexture>>,
+}
+
+/// A `RenderItem` together with the buffers/VAO its vertex data was
+/// uploaded into. Built once in `Renderer::add_item` so a frame only has
+/// to rebind `vao`, not re-specify attribute state.
+struct RenderedItem {
+    item: RenderItem,
+    context: WebGl2RenderingContext,
+    buffer: WebGlBuffer,
+    texture_coord_buffer: Option<WebGlBuffer>,
+    vao: WebGlVertexArrayObject,
+}
+
+impl Drop for RenderedItem {
+    fn drop(&mut self) {
+        self.context.delete_vertex_array(Some(&self.vao));
+        self.context.delete_buffer(Some(&self.buffer));
+        if let Some(texture_coord_buffer) = &self.texture_coord_buffer {
+            self.context.delete_buffer(Some(texture_coord_buffer));
+        }
+    }
+}
+
+/// Owns the GL context plus the named shader programs and render items that
+/// make up a scene.
+pub struct Renderer {
+    context: WebGl2RenderingContext,
+    programs: HashMap<String, ProgramInfo>,
+    items: Vec<RenderedItem>,
+    camera: Camera,
+    elapsed_seconds: Cell<f32>,
+}
+
+impl Renderer {
+    pub fn new(context: WebGl2RenderingContext, camera: Camera) -> Self {
+        Self {
+            context,
+            programs: HashMap::new(),
+            items: Vec::new(),
+            camera,
+            elapsed_seconds: Cell::new(0.0),
+        }
+    }
+
+    /// The GL context backing this renderer, e.g. to build a texture with
+    /// `texture::load_texture` before adding an item that uses it.
+    pub fn context(&self) -> &WebGl2RenderingContext {
+        &self.context
+    }
+
+    /// Recomputes the camera's aspect ratio from `canvas`' current pixel
+    /// size. Call this from a `resize` listener on the DOM canvas path.
+    pub fn handle_resize(&mut self, canvas: &web_sys::HtmlCanvasElement) {
+        self.camera.update_aspect_from_canvas(canvas);
+    }
+
+    /// Sets the camera's aspect ratio directly, for the `OffscreenCanvas`
+    /// path where a worker has no `resize` event and instead learns the new
+    /// dimensions from a message posted by the main thread.
+    pub fn set_aspect(&mut self, aspect: f32) {
+        self.camera.set_aspect(aspect);
+    }
+
+    /// Builds a renderer around an `OffscreenCanvas` instead of the `#canvas`
+    /// DOM element, so the whole render path can run inside a Web Worker
+    /// off the main thread.
+    pub fn from_offscreen_canvas(canvas: OffscreenCanvas) -> Result<Self, JsValue> {
+        let aspect = canvas.width() as f32 / canvas.height() as f32;
+
+        let context = canvas
+            .get_context("webgl2")?
+            .unwrap()
+            .dyn_into::<WebGl2RenderingContext>()?;
+
+        let camera = Camera::new(std::f32::consts::FRAC_PI_4, aspect, 0.1, 100.0);
+
+        Ok(Self::new(context, camera))
+    }
+
+    pub fn register_shader(
+        &mut self,
+        name: &str,
+        vert_src: &str,
+        frag_src: &str,
+    ) -> Result<(), JsValue> {
+        let vert_shader = load_shader(
+            &self.context,
+            WebGl2RenderingContext::VERTEX_SHADER,
+            vert_src,
+        )?;
+        let frag_shader = load_shader(
+            &self.context,
+            WebGl2RenderingContext::FRAGMENT_SHADER,
+            frag_src,
+        )?;
+        let program_info = ProgramInfo::new(&self.context, &vert_shader, &frag_shader)?;
+
+        self.programs.insert(name.to_owned(), program_info);
+        Ok(())
+    }
+
+    /// Uploads `item`'s vertex data into its own buffers/VAO once, so
+    /// `render_scene` only ever has to rebind it.
+    pub fn add_item(&mut self, item: RenderItem) -> Result<(), JsValue> {
+        let program_info = self.programs.get(&item.shader_name).ok_or_else(|| {
+            JsValue::from_str(&format!("unknown shader '{}'", item.shader_name))
+        })?;
+
+        let (buffer, texture_coord_buffer, vao) = init_buffers(
+            &self.context,
+            program_info,
+            &item.vertices,
+            item.texture_coords.as_deref(),
+        )?;
+
+        self.items.push(RenderedItem {
+            item,
+            context: self.context.clone(),
+            buffer,
+            texture_coord_buffer,
+            vao,
+        });
+
+        Ok(())
+    }
+
+    /// Renders one frame. `frame_nr` and `delta_time` (in seconds) let
+    /// items animate via the auto-injected `u_time`/`u_frame` uniforms.
+    pub fn render_scene(&self, frame_nr: u32, delta_time: f32) -> Result<(), JsValue> {
+        let elapsed_seconds = self.elapsed_seconds.get() + delta_time;
+        self.elapsed_seconds.set(elapsed_seconds);
+
+        clear_scene(&self.context);
+
+        for rendered in &self.items {
+            let item = &rendered.item;
+            let program_info = self.programs.get(&item.shader_name).ok_or_else(|| {
+                JsValue::from_str(&format!("unknown shader '{}'", item.shader_name))
+            })?;
+
+            prepare_scene(
+                &self.context,
+                program_info,
+                &rendered.vao,
+                &item.uniforms,
+                item.texture.as_deref(),
+                &self.camera,
+            );
+            program_info.set_uniform(&self.context, "time", &Uniform::Float(elapsed_seconds));
+            program_info.set_uniform(&self.context, "frame", &Uniform::Float(frame_nr as f32));
+
+            draw_scene(&self.context, item.vertices.len() as i32 / 3);
+        }
+
+        Ok(())
+    }
+
+    /// Schedules `render_scene` to run on every `requestAnimationFrame` tick,
+    /// passing a monotonically increasing frame number and the delta-time
+    /// since the previous frame. Returns a handle whose `set(false)` stops
+    /// the loop before the next frame is scheduled.
+    pub fn start_render_loop(renderer: Rc<RefCell<Renderer>>) -> Rc<Cell<bool>> {
+        let running = Rc::new(Cell::new(true));
+        let running_inner = running.clone();
+
+        let frame_nr = Cell::new(0u32);
+        let last_timestamp = Cell::new(None::<f64>);
+
+        let callback = Rc::new(RefCell::new(None::<Closure<dyn FnMut(f64)>>));
+        let callback_handle = callback.clone();
+
+        *callback_handle.borrow_mut() = Some(Closure::new(move |timestamp: f64| {
+            if !running_inner.get() {
+                return;
+            }
+
+            let delta_time = match last_timestamp.get() {
+                Some(previous) => ((timestamp - previous) / 1000.0) as f32,
+                None => 0.0,
+            };
+            last_timestamp.set(Some(timestamp));
+
+            let current_frame = frame_nr.get();
+            frame_nr.set(current_frame + 1);
+
+            if let Err(err) = renderer.borrow().render_scene(current_frame, delta_time) {
+                web_sys::console::error_1(&err);
+            }
+
+            request_animation_frame(callback.borrow().as_ref().unwrap());
+        }));
+
+        request_animation_frame(callback_handle.borrow().as_ref().unwrap());
+
+        running
+    }
+}
+
+/// Schedules `callback` on whatever global scope is running: the `Window`
+/// on the main thread, or the `DedicatedWorkerGlobalScope` when driven from
+/// a Web Worker via `Renderer::from_offscreen_canvas`.
+fn request_animation_frame(callback: &Closure<dyn FnMut(f64)>) {
+    let global = js_sys::global();
+
+    if let Ok(window) = global.clone().dyn_into::<web_sys::Window>() {
+        window
+            .request_animation_frame(callback.as_ref().unchecked_ref())
+            .expect("requestAnimationFrame should be available on window");
+        return;
+    }
+
+    if let Ok(worker_scope) = global.dyn_into::<web_sys::DedicatedWorkerGlobalScope>() {
+        worker_scope
+            .request_animation_frame(callback.as_ref().unchecked_ref())
+            .expect("requestAnimationFrame should be available on the worker scope");
+        return;
+    }
+
+    panic!("no global scope with requestAnimationFrame is available");
+}
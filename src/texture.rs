@@ -0,0 +1,93 @@
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlImageElement, WebGl2RenderingContext, WebGlTexture};
+
+/// Creates a texture and uploads a 1x1 placeholder pixel so it can be bound
+/// immediately, then asynchronously replaces it with the image at `url`
+/// once it has loaded.
+pub fn load_texture(
+    context: &WebGl2RenderingContext,
+    url: &str,
+) -> Result<Rc<WebGlTexture>, JsValue> {
+    let texture = Rc::new(
+        context
+            .create_texture()
+            .ok_or_else(|| JsValue::from_str("failed to create texture"))?,
+    );
+
+    context.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+
+    let placeholder: [u8; 4] = [0, 0, 255, 255];
+    context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+        WebGl2RenderingContext::TEXTURE_2D,
+        0,
+        WebGl2RenderingContext::RGBA as i32,
+        1,
+        1,
+        0,
+        WebGl2RenderingContext::RGBA,
+        WebGl2RenderingContext::UNSIGNED_BYTE,
+        Some(&placeholder),
+    )?;
+
+    let image = Rc::new(HtmlImageElement::new()?);
+
+    {
+        let context = context.clone();
+        let texture = Rc::clone(&texture);
+        let image = Rc::clone(&image);
+
+        let onload = Closure::<dyn FnMut()>::new(move || {
+            context.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+
+            if context
+                .tex_image_2d_with_u32_and_u32_and_html_image_element(
+                    WebGl2RenderingContext::TEXTURE_2D,
+                    0,
+                    WebGl2RenderingContext::RGBA as i32,
+                    WebGl2RenderingContext::RGBA,
+                    WebGl2RenderingContext::UNSIGNED_BYTE,
+                    &image,
+                )
+                .is_err()
+            {
+                return;
+            }
+
+            if is_power_of_2(image.width()) && is_power_of_2(image.height()) {
+                context.generate_mipmap(WebGl2RenderingContext::TEXTURE_2D);
+            } else {
+                // WebGL2 can sample NPOT textures, but only with mipmapping
+                // disabled and edges clamped.
+                context.tex_parameteri(
+                    WebGl2RenderingContext::TEXTURE_2D,
+                    WebGl2RenderingContext::TEXTURE_WRAP_S,
+                    WebGl2RenderingContext::CLAMP_TO_EDGE as i32,
+                );
+                context.tex_parameteri(
+                    WebGl2RenderingContext::TEXTURE_2D,
+                    WebGl2RenderingContext::TEXTURE_WRAP_T,
+                    WebGl2RenderingContext::CLAMP_TO_EDGE as i32,
+                );
+                context.tex_parameteri(
+                    WebGl2RenderingContext::TEXTURE_2D,
+                    WebGl2RenderingContext::TEXTURE_MIN_FILTER,
+                    WebGl2RenderingContext::LINEAR as i32,
+                );
+            }
+        });
+
+        image.set_onload(Some(onload.as_ref().unchecked_ref()));
+        onload.forget();
+    }
+
+    image.set_src(url);
+
+    Ok(texture)
+}
+
+fn is_power_of_2(value: u32) -> bool {
+    value & (value - 1) == 0
+}
@@ -0,0 +1,35 @@
+use web_sys::WebGl2RenderingContext;
+
+use crate::ProgramInfo;
+
+/// A typed uniform value, dispatched to the matching `uniformNfv`/
+/// `uniform_matrix4fv` call by `ProgramInfo::set_uniform`.
+pub enum Uniform {
+    Float(f32),
+    Vec2([f32; 2]),
+    Vec3([f32; 3]),
+    Vec4([f32; 4]),
+    Mat4([f32; 16]),
+}
+
+impl ProgramInfo {
+    pub fn set_uniform(
+        &self,
+        context: &WebGl2RenderingContext,
+        name: &str,
+        value: &Uniform,
+    ) {
+        let location = match self.uniform_locations.get(name) {
+            Some(location) => location,
+            None => return,
+        };
+
+        match value {
+            Uniform::Float(v) => context.uniform1f(Some(location), *v),
+            Uniform::Vec2(v) => context.uniform2fv_with_f32_array(Some(location), v),
+            Uniform::Vec3(v) => context.uniform3fv_with_f32_array(Some(location), v),
+            Uniform::Vec4(v) => context.uniform4fv_with_f32_array(Some(location), v),
+            Uniform::Mat4(v) => context.uniform_matrix4fv_with_f32_array(Some(location), false, v),
+        }
+    }
+}